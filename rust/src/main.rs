@@ -2,7 +2,8 @@
 //!
 //! Reads fundamental discriminant files (cl[a]mod[m].[index].gz) and
 //! index-ℓ² files (cl[a]mod[m]l[ell].[index].gz) to find discriminants
-//! where a ℤ/ℓ^N factor grows to ℤ/ℓ^(N+1).
+//! where a ℤ/ℓ^N factor grows to ℤ/ℓ^(N+K), for a configurable jump K
+//! (default K=1, i.e. the single-step N -> N+1 transition).
 
 use clap::Parser;
 use flate2::read::GzDecoder;
@@ -10,7 +11,7 @@ use rayon::prelude::*;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser, Debug)]
 #[command(name = "ell_growth")]
@@ -19,9 +20,9 @@ struct Args {
     /// Base folder containing cl[a]mod[m]/ and cl[a]mod[m]l[ell]/ directories
     folder: PathBuf,
 
-    /// Prime ℓ for growth analysis
+    /// Prime(s) ℓ for growth analysis, comma-separated (e.g. "2,3,5,7")
     #[arg(short, long)]
-    ell: u64,
+    ell: String,
 
     /// Maximum |discriminant|
     #[arg(short = 'D', long)]
@@ -35,14 +36,53 @@ struct Args {
     #[arg(short, long, default_value_t = false)]
     verbose: bool,
 
-    /// Growth detection mode:
-    /// - "strict": fund has ℓ^N, loses one, and gains one ℓ^(N+1) in ell
-    /// - "any": fund has ℓ^N and ell has ℓ^(N+1) (regardless of other factors)
-    /// - "net": total ℓ^(N+1) count increases (fund -> ell)
+    /// Growth detection mode (N+K is the jump target; see --jump):
+    /// - "strict": fund has ℓ^N, loses one, and gains one ℓ^(N+K) in ell
+    /// - "any": fund has ℓ^N and ell has ℓ^(N+K) (regardless of other factors)
+    /// - "net": total ℓ^(N+K) count increases (fund -> ell)
     #[arg(long, default_value = "strict")]
     mode: String,
+
+    /// Output format: "text" (human summary), "json", or "csv"
+    #[arg(long, default_value = "text")]
+    format: String,
+
+    /// Write output to PATH instead of stdout (only affects --format json/csv)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Abort on fund/ell discriminant misalignment instead of skipping the
+    /// unmatched rows. Without it an isolated unmatched row is skipped, but
+    /// two consecutive unmatched rows on the same side still abort that file
+    /// pair, and its results for every prime are left out of the totals
+    #[arg(long, default_value_t = false)]
+    strict_align: bool,
+
+    /// Report Cohen-Lenstra expected-vs-observed goodness-of-fit (chi-squared
+    /// per N/kron cell, with aggregate) instead of the usual report.
+    /// Only supported with --format text.
+    #[arg(long, default_value_t = false)]
+    heuristic: bool,
+
+    /// Minimum per-cell sample size (with_factor) to include in the
+    /// --heuristic chi-squared report; smaller cells are skipped to avoid
+    /// inflating chi-squared from tiny counts
+    #[arg(long, default_value_t = 30)]
+    min_sample: u64,
+
+    /// Jump size K: count discriminants where a ℤ/ℓ^N factor grows all the
+    /// way to ℤ/ℓ^(N+K), instead of the default single-step N -> N+1.
+    /// Must be between 1 and MAX_JUMP.
+    #[arg(long, default_value_t = 1)]
+    jump: u32,
 }
 
+/// Largest supported --jump. A ℓ-adic valuation of a u64 invariant can never
+/// exceed 63 (2^64 already overflows u64), so no N+K target beyond that can
+/// ever match a real factor; capping here also keeps `target_n + jump`
+/// additions in the analysis below far from u32 overflow.
+const MAX_JUMP: u32 = 63;
+
 /// All congruence classes for fundamental discriminants
 const CONGRUENCE_CLASSES: [(i32, i32); 4] = [
     (8, 16), // D ≡ 8 mod 16
@@ -105,6 +145,11 @@ fn parse_ell_line(line: &str) -> Option<(i64, i8, Vec<u64>)> {
     Some((dist, kron, invariants))
 }
 
+/// Parse a comma-separated list of primes, e.g. "2,3,5,7"
+fn parse_ells(s: &str) -> Result<Vec<u64>, std::num::ParseIntError> {
+    s.split(',').map(|p| p.trim().parse()).collect()
+}
+
 /// Results from processing one file pair
 /// Now tracks counts for ALL values of N
 #[derive(Debug, Default, Clone)]
@@ -112,15 +157,32 @@ struct FileResults {
     /// Total discriminants processed
     total: u64,
     /// For each N: (with_factor, with_growth)
-    /// Key is N, value is (count with ℓ^N factor, count with growth to ℓ^(N+1))
+    /// Key is N, value is (count with ℓ^N factor, count with growth to ℓ^(N+K))
     by_n: HashMap<u32, (u64, u64)>,
     /// Breakdown by (N, Kronecker symbol): ((N, kron), (with_factor, with_growth))
     by_n_kron: HashMap<(u32, i8), (u64, u64)>,
+    /// Discriminant rows that could not be matched across the fund/ell files
+    /// during the merge-join (missing/extra line in one file)
+    unmatched: u64,
+    /// Number of (file index, ell) pairs where the fund/ell row counts
+    /// disagreed outright. This is a coarser signal than `unmatched`: a
+    /// single dropped/duplicated ell-file line whose `dist` coincidentally
+    /// matches its neighbor's can realign the merge-join's cumulative
+    /// discriminants and get paired as a spurious match without ever
+    /// tripping the row-by-row skip detection. Such a file pair still has a
+    /// row-count mismatch and is counted here; its leftover rows also land in
+    /// `unmatched`, where they look the same as any ordinary skipped row
+    mismatched_files: u64,
 }
 
+/// Per-prime results for one congruence class (or the grand total), keyed by ℓ
+type ClassResults = HashMap<u64, FileResults>;
+
 impl FileResults {
     fn merge(&mut self, other: FileResults) {
         self.total += other.total;
+        self.unmatched += other.unmatched;
+        self.mismatched_files += other.mismatched_files;
         for (n, (factor, growth)) in other.by_n {
             let entry = self.by_n.entry(n).or_insert((0, 0));
             entry.0 += factor;
@@ -134,250 +196,597 @@ impl FileResults {
     }
 }
 
-/// Process a single file pair
-fn process_file_pair(
-    folder: &PathBuf,
-    a: i32,
+/// A fundamental-file row advanced to its absolute discriminant
+struct FundRow {
+    d: i64,
+    invariants: Vec<u64>,
+}
+
+/// An ell-file row advanced to its absolute discriminant
+struct EllRow {
+    d: i64,
+    kron: i8,
+    invariants: Vec<u64>,
+}
+
+/// Read and parse the next fundamental-file line, advancing `d` by its distance
+fn next_fund_row(
+    lines: &mut std::io::Lines<BufReader<GzDecoder<File>>>,
+    d: &mut i64,
     m: i32,
-    ell: u64,
-    index: i64,
-    d_total: i64,
-    verbose: bool,
-    mode: &str,
-) -> Result<FileResults, Box<dyn std::error::Error + Send + Sync>> {
-    let fund_path = folder
-        .join(format!("cl{}mod{}", a, m))
-        .join(format!("cl{}mod{}.{}.gz", a, m, index));
+) -> Result<Option<FundRow>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(line) = lines.next() else {
+        return Ok(None);
+    };
+    let line = line?;
+    let (dist, _h, invariants) = parse_fundamental_line(&line)
+        .ok_or_else(|| format!("malformed fundamental line: {:?}", line))?;
+    *d += dist * (m as i64);
+    Ok(Some(FundRow { d: *d, invariants }))
+}
 
-    let ell_path = folder
-        .join(format!("cl{}mod{}l{}", a, m, ell))
-        .join(format!("cl{}mod{}l{}.{}.gz", a, m, ell, index));
+/// Read and parse the next ell-file line, advancing `d` by its distance
+fn next_ell_row(
+    lines: &mut std::io::Lines<BufReader<GzDecoder<File>>>,
+    d: &mut i64,
+    m: i32,
+) -> Result<Option<EllRow>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(line) = lines.next() else {
+        return Ok(None);
+    };
+    let line = line?;
+    let (dist, kron, invariants) =
+        parse_ell_line(&line).ok_or_else(|| format!("malformed ell line: {:?}", line))?;
+    *d += dist * (m as i64);
+    Ok(Some(EllRow {
+        d: *d,
+        kron,
+        invariants,
+    }))
+}
 
-    // Open both files
-    let fund_file = File::open(&fund_path)?;
-    let ell_file = File::open(&ell_path)?;
+/// Decode a fundamental file once, into every row advanced to its absolute
+/// discriminant. Reused against each prime's ell file so the (expensive) gz
+/// decode + parse only happens once per file pair.
+fn read_fund_rows(
+    path: &Path,
+    d_start: i64,
+    m: i32,
+) -> Result<Vec<FundRow>, Box<dyn std::error::Error + Send + Sync>> {
+    let file = File::open(path)?;
+    let mut lines = BufReader::new(GzDecoder::new(file)).lines();
+    let mut d = d_start;
+    let mut rows = Vec::new();
+    while let Some(row) = next_fund_row(&mut lines, &mut d, m)? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
 
-    let fund_reader = BufReader::new(GzDecoder::new(fund_file));
-    let ell_reader = BufReader::new(GzDecoder::new(ell_file));
+/// Decode an ell file in full, mirroring `read_fund_rows`. Materializing both
+/// sides upfront (rather than streaming the ell file lazily) lets the merge-join
+/// compare total row counts before pairing a single row, so a length mismatch
+/// between the two files is caught before any row is paired.
+fn read_ell_rows(
+    path: &Path,
+    d_start: i64,
+    m: i32,
+) -> Result<Vec<EllRow>, Box<dyn std::error::Error + Send + Sync>> {
+    let file = File::open(path)?;
+    let mut lines = BufReader::new(GzDecoder::new(file)).lines();
+    let mut d = d_start;
+    let mut rows = Vec::new();
+    while let Some(row) = next_ell_row(&mut lines, &mut d, m)? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
 
-    let mut results = FileResults::default();
+/// Growth-detection knobs threaded through the merge-join and analysis
+/// functions below, bundled up so adding a flag doesn't grow every call
+/// site's argument list
+struct GrowthOptions {
+    mode: String,
+    jump: u32,
+    strict_align: bool,
+    verbose: bool,
+}
 
-    // Starting discriminant for this file
-    let mut d_fund: i64 = index * d_total * (m as i64) + (a as i64);
+/// Analyze one matched fund/ell row pair and fold its per-N growth counts into `results`
+fn record_matched_pair(
+    results: &mut FileResults,
+    d: i64,
+    kron: i8,
+    fund_invariants: &[u64],
+    ell_invariants: &[u64],
+    ell: u64,
+    opts: &GrowthOptions,
+) {
+    results.total += 1;
 
-    // Process lines in parallel (but must be synchronized since discriminant tracking is sequential)
-    for (fund_line, ell_line) in fund_reader.lines().zip(ell_reader.lines()) {
-        let fund_line = fund_line?;
-        let ell_line = ell_line?;
+    // Compute ℓ-profiles
+    let fund_profile = ell_profile(fund_invariants, ell);
+    let ell_prof = ell_profile(ell_invariants, ell);
 
-        let Some((dist_fund, _h, fund_invariants)) = parse_fundamental_line(&fund_line) else {
-            continue;
-        };
-        let Some((dist_ell, kron, ell_invariants)) = parse_ell_line(&ell_line) else {
+    // Find the maximum N we need to check (max valuation in either profile)
+    let max_n = fund_profile
+        .iter()
+        .chain(ell_prof.iter())
+        .copied()
+        .max()
+        .unwrap_or(0);
+
+    // Check each N from 1 to max_n
+    for target_n in 1..=max_n {
+        let target_n1 = target_n + opts.jump;
+        let fund_n_count = count_factor(&fund_profile, target_n);
+        let fund_n1_count = count_factor(&fund_profile, target_n1);
+        let ell_n_count = count_factor(&ell_prof, target_n);
+        let ell_n1_count = count_factor(&ell_prof, target_n1);
+
+        // Check if fundamental has factor of order ℓ^N
+        if fund_n_count == 0 {
             continue;
+        }
+
+        // Record that this discriminant has an ℓ^N factor
+        let n_entry = results.by_n.entry(target_n).or_insert((0, 0));
+        n_entry.0 += 1;
+        let nk_entry = results.by_n_kron.entry((target_n, kron)).or_insert((0, 0));
+        nk_entry.0 += 1;
+
+        // Detect growth based on mode
+        let growth = match opts.mode.as_str() {
+            "strict" => {
+                // Strict: one ℓ^N factor disappears AND one ℓ^(N+K) factor appears
+                ell_n1_count > fund_n1_count && ell_n_count < fund_n_count
+            }
+            "any" => {
+                // Any: fund has ℓ^N and ell has ℓ^(N+K)
+                fund_n_count > 0 && ell_n1_count > 0
+            }
+            "net" => {
+                // Net: total ℓ^(N+K) count increases
+                ell_n1_count > fund_n1_count
+            }
+            _ => {
+                // Default to strict
+                ell_n1_count > fund_n1_count && ell_n_count < fund_n_count
+            }
         };
 
-        // Sanity check: distances should match
-        if dist_fund != dist_ell {
-            eprintln!(
-                "Warning: distance mismatch at D={}: fund={}, ell={}",
-                d_fund, dist_fund, dist_ell
-            );
-        }
+        if growth {
+            n_entry.1 += 1;
+            nk_entry.1 += 1;
 
-        // Update discriminant
-        d_fund += dist_fund * (m as i64);
-        results.total += 1;
-
-        // Compute ℓ-profiles
-        let fund_profile = ell_profile(&fund_invariants, ell);
-        let ell_prof = ell_profile(&ell_invariants, ell);
-
-        // Find the maximum N we need to check (max valuation in either profile)
-        let max_n = fund_profile
-            .iter()
-            .chain(ell_prof.iter())
-            .copied()
-            .max()
-            .unwrap_or(0);
-
-        // Check each N from 1 to max_n
-        for target_n in 1..=max_n {
-            let fund_n_count = count_factor(&fund_profile, target_n);
-            let fund_n1_count = count_factor(&fund_profile, target_n + 1);
-            let ell_n_count = count_factor(&ell_prof, target_n);
-            let ell_n1_count = count_factor(&ell_prof, target_n + 1);
-
-            // Check if fundamental has factor of order ℓ^N
-            if fund_n_count == 0 {
-                continue;
+            if opts.verbose {
+                println!(
+                    "D={}: N={}, kron={}, fund_profile={:?}, ell_profile={:?}",
+                    d, target_n, kron, fund_profile, ell_prof
+                );
             }
+        }
+    }
+}
 
-            // Record that this discriminant has an ℓ^N factor
-            let n_entry = results.by_n.entry(target_n).or_insert((0, 0));
-            n_entry.0 += 1;
-            let nk_entry = results.by_n_kron.entry((target_n, kron)).or_insert((0, 0));
-            nk_entry.0 += 1;
-
-            // Detect growth based on mode
-            let growth = match mode {
-                "strict" => {
-                    // Strict: one ℓ^N factor disappears AND one ℓ^(N+1) factor appears
-                    ell_n1_count > fund_n1_count && ell_n_count < fund_n_count
-                }
-                "any" => {
-                    // Any: fund has ℓ^N and ell has ℓ^(N+1)
-                    fund_n_count > 0 && ell_n1_count > 0
-                }
-                "net" => {
-                    // Net: total ℓ^(N+1) count increases
-                    ell_n1_count > fund_n1_count
-                }
-                _ => {
-                    // Default to strict
-                    ell_n1_count > fund_n1_count && ell_n_count < fund_n_count
-                }
-            };
+/// Which side the merge-join last had to skip past without finding a match
+#[derive(PartialEq, Eq)]
+enum SkipSide {
+    Fund,
+    Ell,
+}
 
-            if growth {
-                n_entry.1 += 1;
-                nk_entry.1 += 1;
+/// Merge-join a prime's (already-decoded) ell rows against the already-decoded
+/// fundamental rows for the same file pair
+fn process_one_prime(
+    fund_rows: &[FundRow],
+    ell_rows: &[EllRow],
+    ell: u64,
+    opts: &GrowthOptions,
+) -> Result<FileResults, Box<dyn std::error::Error + Send + Sync>> {
+    // A row-count mismatch is itself evidence of a dropped/extra line somewhere
+    // in one of the files; it can't be pinpointed from the totals alone (a
+    // matching pair of D values elsewhere in the files could still coincide by
+    // chance and get paired with the wrong invariants), so surface it loudly
+    // rather than letting it hide inside the `unmatched` tally.
+    if fund_rows.len() != ell_rows.len() {
+        eprintln!(
+            "warning: ell={} row count mismatch: {} fundamental rows vs {} ell rows \
+             (a dropped or duplicated line may desync the pairing below)",
+            ell,
+            fund_rows.len(),
+            ell_rows.len()
+        );
+        if opts.strict_align {
+            return Err(format!(
+                "row count mismatch for ell={}: {} fundamental rows vs {} ell rows",
+                ell,
+                fund_rows.len(),
+                ell_rows.len()
+            )
+            .into());
+        }
+    }
+
+    let mut results = FileResults::default();
+    if fund_rows.len() != ell_rows.len() {
+        results.mismatched_files = 1;
+    }
+
+    let mut fund_idx = 0usize;
+    let mut ell_idx = 0usize;
 
-                if verbose {
-                    println!(
-                        "D={}: N={}, kron={}, fund_profile={:?}, ell_profile={:?}",
-                        d_fund, target_n, kron, fund_profile, ell_prof
-                    );
+    // Consecutive same-side skips since the last real match: more than one in
+    // a row means the files haven't merely lost a single line, they've drifted
+    // in a way a single D-value comparison can't be trusted to resolve, so we
+    // abort rather than keep guessing.
+    let mut skip_streak = 0u32;
+    let mut skip_side: Option<SkipSide> = None;
+
+    loop {
+        let (fund_row, ell_row) = match (fund_rows.get(fund_idx), ell_rows.get(ell_idx)) {
+            (None, None) => break,
+            (Some(_), None) => {
+                if opts.strict_align {
+                    return Err(format!(
+                        "unmatched fundamental discriminant at D={} (ell file exhausted)",
+                        fund_rows[fund_idx].d
+                    )
+                    .into());
                 }
+                results.unmatched += 1;
+                fund_idx += 1;
+                continue;
             }
+            (None, Some(_)) => {
+                if opts.strict_align {
+                    return Err(format!(
+                        "unmatched ell discriminant at D={} (fundamental file exhausted)",
+                        ell_rows[ell_idx].d
+                    )
+                    .into());
+                }
+                results.unmatched += 1;
+                ell_idx += 1;
+                continue;
+            }
+            (Some(f), Some(e)) => (f, e),
+        };
+
+        if fund_row.d < ell_row.d {
+            if opts.strict_align {
+                return Err(format!(
+                    "unmatched fundamental discriminant at D={} (next ell D={})",
+                    fund_row.d, ell_row.d
+                )
+                .into());
+            }
+            skip_streak = if skip_side == Some(SkipSide::Fund) {
+                skip_streak + 1
+            } else {
+                1
+            };
+            skip_side = Some(SkipSide::Fund);
+            if skip_streak > 1 {
+                return Err(format!(
+                    "{} consecutive unmatched fundamental discriminants up to D={} \
+                     (next ell D={}); aborting instead of guessing at realignment",
+                    skip_streak, fund_row.d, ell_row.d
+                )
+                .into());
+            }
+            results.unmatched += 1;
+            fund_idx += 1;
+            continue;
+        }
+        if ell_row.d < fund_row.d {
+            if opts.strict_align {
+                return Err(format!(
+                    "unmatched ell discriminant at D={} (next fundamental D={})",
+                    ell_row.d, fund_row.d
+                )
+                .into());
+            }
+            skip_streak = if skip_side == Some(SkipSide::Ell) {
+                skip_streak + 1
+            } else {
+                1
+            };
+            skip_side = Some(SkipSide::Ell);
+            if skip_streak > 1 {
+                return Err(format!(
+                    "{} consecutive unmatched ell discriminants up to D={} \
+                     (next fundamental D={}); aborting instead of guessing at realignment",
+                    skip_streak, ell_row.d, fund_row.d
+                )
+                .into());
+            }
+            results.unmatched += 1;
+            ell_idx += 1;
+            continue;
         }
+
+        // Discriminants coincide: record and analyze this pair
+        record_matched_pair(
+            &mut results,
+            fund_row.d,
+            ell_row.kron,
+            &fund_row.invariants,
+            &ell_row.invariants,
+            ell,
+            opts,
+        );
+
+        skip_streak = 0;
+        skip_side = None;
+        fund_idx += 1;
+        ell_idx += 1;
     }
 
     Ok(results)
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+/// Process a single file pair across every requested prime. The fundamental
+/// file is decoded once and merge-joined against each prime's ell file in turn.
+fn process_file_pair(
+    folder: &Path,
+    a: i32,
+    m: i32,
+    ells: &[u64],
+    index: i64,
+    d_total: i64,
+    opts: &GrowthOptions,
+) -> Result<ClassResults, Box<dyn std::error::Error + Send + Sync>> {
+    let fund_path = folder
+        .join(format!("cl{}mod{}", a, m))
+        .join(format!("cl{}mod{}.{}.gz", a, m, index));
 
-    println!("ℓ-adic growth analysis");
-    println!("======================");
-    println!("folder: {:?}", args.folder);
-    println!("ℓ={}", args.ell);
-    println!("D_max={}, files={}", args.d_max, args.files);
-    println!(
-        "Target: all N where ℤ/{}^N ℤ → ℤ/{}^(N+1)ℤ growth",
-        args.ell, args.ell
-    );
-    println!("Detection mode: {}", args.mode);
-    println!();
+    // Starting discriminant for this file; fund and each ell file track their
+    // own running discriminant since a missing/extra line in either file
+    // would otherwise desynchronize a positional pairing.
+    let d_start: i64 = index * d_total * (m as i64) + (a as i64);
+    let fund_rows = read_fund_rows(&fund_path, d_start, m)?;
 
-    // Aggregate results across all congruence classes
-    let mut grand_total = FileResults::default();
-    let mut by_class: Vec<((i32, i32), FileResults)> = Vec::new();
+    let mut per_prime = HashMap::new();
+    for &ell in ells {
+        let ell_path = folder
+            .join(format!("cl{}mod{}l{}", a, m, ell))
+            .join(format!("cl{}mod{}l{}.{}.gz", a, m, ell, index));
 
-    for (a, m) in CONGRUENCE_CLASSES {
-        let d_total = args.d_max / (args.files * m as i64);
+        let ell_rows = read_ell_rows(&ell_path, d_start, m)?;
+        let results = process_one_prime(&fund_rows, &ell_rows, ell, opts)?;
+        per_prime.insert(ell, results);
+    }
 
-        println!("Processing {} mod {} ...", a, m);
+    Ok(per_prime)
+}
 
-        // Process all file pairs in parallel for this congruence class
-        let mode = args.mode.clone();
-        let results: Vec<_> = (0..args.files)
-            .into_par_iter()
-            .map(|index| {
-                match process_file_pair(
-                    &args.folder,
-                    a,
-                    m,
-                    args.ell,
-                    index,
-                    d_total,
-                    args.verbose,
-                    &mode,
-                ) {
-                    Ok(r) => r,
-                    Err(e) => {
-                        eprintln!("Error processing file {} for {}mod{}: {}", index, a, m, e);
-                        FileResults::default()
-                    }
-                }
-            })
-            .collect();
+/// Serialize one `FileResults` bucket as a JSON object: `{"total":.., "by_n":{"N":{...}}}`
+fn results_to_json(results: &FileResults) -> String {
+    let mut n_values: Vec<_> = results.by_n.keys().copied().collect();
+    n_values.sort_unstable();
 
-        // Merge results for this congruence class
-        let mut class_results = FileResults::default();
-        for r in results {
-            class_results.merge(r);
+    let mut by_n_json = Vec::new();
+    for n in n_values {
+        let (with_factor, with_growth) = results.by_n.get(&n).copied().unwrap_or((0, 0));
+        let rate = if with_factor > 0 {
+            with_growth as f64 / with_factor as f64
+        } else {
+            0.0
+        };
+
+        let mut kron_json = Vec::new();
+        for kron in [-1i8, 0, 1] {
+            if let Some(&(kf, kg)) = results.by_n_kron.get(&(n, kron)) {
+                let krate = if kf > 0 { kg as f64 / kf as f64 } else { 0.0 };
+                kron_json.push(format!(
+                    "\"{}\":{{\"with_factor\":{},\"with_growth\":{},\"rate\":{}}}",
+                    kron, kf, kg, krate
+                ));
+            }
         }
 
-        grand_total.merge(class_results.clone());
-        by_class.push(((a, m), class_results));
+        by_n_json.push(format!(
+            "\"{}\":{{\"with_factor\":{},\"with_growth\":{},\"rate\":{},\"by_kron\":{{{}}}}}",
+            n,
+            with_factor,
+            with_growth,
+            rate,
+            kron_json.join(",")
+        ));
     }
 
-    // Output per-class summaries
-    println!();
-    println!("Results by congruence class");
-    println!("===========================");
-    for ((a, m), results) in &by_class {
-        println!();
-        println!("{} mod {}:", a, m);
-        println!("  Total discriminants: {}", results.total);
+    format!(
+        "{{\"total\":{},\"unmatched\":{},\"mismatched_files\":{},\"by_n\":{{{}}}}}",
+        results.total,
+        results.unmatched,
+        results.mismatched_files,
+        by_n_json.join(",")
+    )
+}
 
-        // Get all N values and sort them
-        let mut n_values: Vec<_> = results.by_n.keys().copied().collect();
-        n_values.sort();
+/// Build the full JSON report: `by_class` keyed by "{a}mod{m}", plus `grand_total`.
+/// `opts` and `requested_ells` are embedded as a top-level `"meta"` object so
+/// the payload is self-describing (which --mode/--jump/--ell produced these
+/// numbers) without the reader having to remember the CLI invocation that
+/// generated it
+fn build_json_report(
+    by_class: &[((i32, i32), ClassResults)],
+    grand_total: &ClassResults,
+    opts: &GrowthOptions,
+    requested_ells: &[u64],
+) -> String {
+    let primes_json = |per_prime: &ClassResults| -> String {
+        let mut ells: Vec<_> = per_prime.keys().copied().collect();
+        ells.sort_unstable();
+        let objs: Vec<String> = ells
+            .into_iter()
+            .map(|ell| format!("\"{}\":{}", ell, results_to_json(&per_prime[&ell])))
+            .collect();
+        format!("{{{}}}", objs.join(","))
+    };
 
-        for n in n_values {
-            let (with_factor, with_growth) = results.by_n.get(&n).copied().unwrap_or((0, 0));
-            println!(
-                "  N={}: with ℓ^{} factor: {}, with growth to ℓ^{}: {} ({:.2}%)",
-                n,
-                n,
-                with_factor,
-                n + 1,
-                with_growth,
-                if with_factor > 0 {
-                    100.0 * with_growth as f64 / with_factor as f64
-                } else {
-                    0.0
-                }
-            );
+    let classes: Vec<String> = by_class
+        .iter()
+        .map(|((a, m), per_prime)| format!("\"{}mod{}\":{}", a, m, primes_json(per_prime)))
+        .collect();
+
+    let ell_json = requested_ells
+        .iter()
+        .map(|ell| ell.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!(
+        "{{\"meta\":{{\"mode\":\"{}\",\"jump\":{},\"ell\":[{}]}},\"by_class\":{{{}}},\"grand_total\":{}}}\n",
+        opts.mode,
+        opts.jump,
+        ell_json,
+        classes.join(","),
+        primes_json(grand_total)
+    )
+}
 
-            // Kronecker breakdown for this N
+/// Build the full CSV report: one row per (a, m, ell, N, kron), plus an "ALL"
+/// grand-total section. `total`/`unmatched`/`mismatched_files` are per
+/// (a, m, ell) bucket and repeated on every row of that bucket, mirroring the
+/// JSON export's per-bucket fields. `mode`/`jump`/`requested_ells` are the
+/// run-wide settings, also repeated on every row for the same reason: a CSV
+/// file on its own should identify which --mode/--jump/--ell produced it
+fn build_csv_report(
+    by_class: &[((i32, i32), ClassResults)],
+    grand_total: &ClassResults,
+    opts: &GrowthOptions,
+    requested_ells: &[u64],
+) -> String {
+    let mut out = String::from(
+        "mode,jump,requested_ells,a,m,ell,total,unmatched,mismatched_files,N,kron,with_factor,with_growth,rate\n",
+    );
+    let requested_ells_str = requested_ells
+        .iter()
+        .map(|ell| ell.to_string())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let mut append_rows = |a: &str, m: &str, ell: u64, results: &FileResults| {
+        let mut n_values: Vec<_> = results.by_n_kron.keys().map(|(n, _)| *n).collect();
+        n_values.sort_unstable();
+        n_values.dedup();
+        for n in n_values {
             for kron in [-1i8, 0, 1] {
                 if let Some(&(kf, kg)) = results.by_n_kron.get(&(n, kron)) {
-                    let kron_name = match kron {
-                        -1 => "inert",
-                        0 => "ramified",
-                        1 => "split",
-                        _ => "unknown",
-                    };
-                    println!(
-                        "      kron={:2} ({}): factor={}, growth={} ({:.2}%)",
+                    let rate = if kf > 0 { kg as f64 / kf as f64 } else { 0.0 };
+                    out.push_str(&format!(
+                        "{},{},{},{},{},{},{},{},{},{},{},{},{},{:.6}\n",
+                        opts.mode,
+                        opts.jump,
+                        requested_ells_str,
+                        a,
+                        m,
+                        ell,
+                        results.total,
+                        results.unmatched,
+                        results.mismatched_files,
+                        n,
                         kron,
-                        kron_name,
                         kf,
                         kg,
-                        if kf > 0 {
-                            100.0 * kg as f64 / kf as f64
-                        } else {
-                            0.0
-                        }
-                    );
+                        rate
+                    ));
                 }
             }
         }
+    };
+
+    for ((a, m), per_prime) in by_class {
+        let mut ells: Vec<_> = per_prime.keys().copied().collect();
+        ells.sort_unstable();
+        for ell in ells {
+            append_rows(&a.to_string(), &m.to_string(), ell, &per_prime[&ell]);
+        }
     }
 
-    // Output grand totals
-    println!();
-    println!("Grand Total (all congruence classes)");
-    println!("====================================");
-    println!("Total discriminants: {}", grand_total.total);
+    let mut grand_ells: Vec<_> = grand_total.keys().copied().collect();
+    grand_ells.sort_unstable();
+    for ell in grand_ells {
+        append_rows("ALL", "ALL", ell, &grand_total[&ell]);
+    }
+
+    out
+}
+
+/// Print the per-congruence-class human-readable report for one prime
+fn print_class_report(results: &FileResults, jump: u32) {
+    println!("  Total discriminants: {}", results.total);
+    println!("  Unmatched fund/ell rows: {}", results.unmatched);
+    if results.mismatched_files > 0 {
+        println!(
+            "  WARNING: {} file(s) had a fund/ell row count mismatch; matched pairs in \
+             this bucket may include a mispairing the skip detection couldn't catch",
+            results.mismatched_files
+        );
+    }
+
+    let mut n_values: Vec<_> = results.by_n.keys().copied().collect();
+    n_values.sort();
+
+    for n in n_values {
+        let (with_factor, with_growth) = results.by_n.get(&n).copied().unwrap_or((0, 0));
+        println!(
+            "  N={}: with ℓ^{} factor: {}, with growth to ℓ^{}: {} ({:.2}%)",
+            n,
+            n,
+            with_factor,
+            n + jump,
+            with_growth,
+            if with_factor > 0 {
+                100.0 * with_growth as f64 / with_factor as f64
+            } else {
+                0.0
+            }
+        );
+
+        // Kronecker breakdown for this N
+        for kron in [-1i8, 0, 1] {
+            if let Some(&(kf, kg)) = results.by_n_kron.get(&(n, kron)) {
+                let kron_name = match kron {
+                    -1 => "inert",
+                    0 => "ramified",
+                    1 => "split",
+                    _ => "unknown",
+                };
+                println!(
+                    "      kron={:2} ({}): factor={}, growth={} ({:.2}%)",
+                    kron,
+                    kron_name,
+                    kf,
+                    kg,
+                    if kf > 0 {
+                        100.0 * kg as f64 / kf as f64
+                    } else {
+                        0.0
+                    }
+                );
+            }
+        }
+    }
+}
+
+/// Print the grand-total human-readable report (summary table + detailed
+/// breakdown) for one prime
+fn print_grand_total_report(results: &FileResults, ell: u64, jump: u32) {
+    println!("Total discriminants: {}", results.total);
+    println!("Unmatched fund/ell rows: {}", results.unmatched);
+    if results.mismatched_files > 0 {
+        println!(
+            "WARNING: {} file(s) had a fund/ell row count mismatch; matched pairs in \
+             this total may include a mispairing the skip detection couldn't catch",
+            results.mismatched_files
+        );
+    }
     println!();
 
-    // Get all N values and sort them
-    let mut n_values: Vec<_> = grand_total.by_n.keys().copied().collect();
+    let mut n_values: Vec<_> = results.by_n.keys().copied().collect();
     n_values.sort();
 
     println!("Summary table:");
@@ -387,7 +796,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
     println!("{}", "-".repeat(42));
     for n in &n_values {
-        let (with_factor, with_growth) = grand_total.by_n.get(n).copied().unwrap_or((0, 0));
+        let (with_factor, with_growth) = results.by_n.get(n).copied().unwrap_or((0, 0));
         println!(
             "{:>4} {:>12} {:>12} {:>9.4}%",
             n,
@@ -404,9 +813,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!();
     println!("Detailed breakdown by N and Kronecker symbol:");
     for n in n_values {
-        let (with_factor, with_growth) = grand_total.by_n.get(&n).copied().unwrap_or((0, 0));
+        let (with_factor, with_growth) = results.by_n.get(&n).copied().unwrap_or((0, 0));
         println!();
-        println!("N={}: ℤ/{}^{}ℤ → ℤ/{}^{}ℤ", n, args.ell, n, args.ell, n + 1);
+        println!("N={}: ℤ/{}^{}ℤ → ℤ/{}^{}ℤ", n, ell, n, ell, n + jump);
         println!(
             "  Total: with_factor={}, with_growth={} ({:.4}%)",
             with_factor,
@@ -419,7 +828,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
 
         for kron in [-1i8, 0, 1] {
-            if let Some(&(kf, kg)) = grand_total.by_n_kron.get(&(n, kron)) {
+            if let Some(&(kf, kg)) = results.by_n_kron.get(&(n, kron)) {
                 let kron_name = match kron {
                     -1 => "inert",
                     0 => "ramified",
@@ -441,6 +850,471 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
     }
+}
+
+/// Partial Cohen-Lenstra product ∏_{k=1}^{n} (1 − ℓ^{-k}); η_0(ℓ) = 1, and the
+/// limit as n→∞ is η_∞(ℓ). The factors approach 1 geometrically in `ell`;
+/// η_n(ℓ)/η_∞(ℓ) differs from 1 by about ℓ^{-n}, so for ℓ=2 roughly 53 terms
+/// are needed before η_n(ℓ) equals η_∞(ℓ) to f64 precision.
+fn eta_partial(ell: u64, n: u32) -> f64 {
+    let mut eta = 1.0;
+    let mut ell_k = ell as f64;
+    for _ in 0..n {
+        eta *= 1.0 - 1.0 / ell_k;
+        ell_k *= ell as f64;
+    }
+    eta
+}
+
+/// Cohen-Lenstra-heuristic estimate of the probability that a ℤ/ℓ^Nℤ factor
+/// extends to ℤ/ℓ^(N+K)ℤ, expressed as a ratio of the η_∞(ℓ) partial products
+/// (the predicted proportion of discriminants carrying a ℤ/ℓ^(N+K) factor over
+/// the proportion carrying a ℤ/ℓ^N factor): p(N) = ℓ^{-K} · η_N(ℓ) / η_{N+K}(ℓ).
+fn expected_growth_probability(ell: u64, n: u32, jump: u32) -> f64 {
+    let eta_n = eta_partial(ell, n);
+    let eta_n1 = eta_partial(ell, n + jump);
+    (1.0 / (ell as f64).powi(jump as i32)) * (eta_n / eta_n1)
+}
+
+/// Clamp a probability away from the degenerate 0/1 endpoints so chi-squared
+/// terms stay finite
+fn clamp_probability(p: f64) -> f64 {
+    const EPS: f64 = 1e-9;
+    p.clamp(EPS, 1.0 - EPS)
+}
+
+/// Print the Cohen-Lenstra expected-vs-observed goodness-of-fit report for one
+/// `FileResults` bucket: observed rate, expected p, and χ² contribution per
+/// (N, kron) cell, skipping cells below `min_sample`, with the aggregate χ² at
+/// the bottom.
+fn print_heuristic_report(
+    label: &str,
+    results: &FileResults,
+    ell: u64,
+    min_sample: u64,
+    jump: u32,
+) {
+    println!();
+    println!("{}", label);
+    println!("{}", "-".repeat(label.chars().count()));
+    println!(
+        "{:>4} {:>6} {:>10} {:>10} {:>10} {:>10}",
+        "N", "kron", "n", "observed", "expected_p", "chi2"
+    );
+    println!("{}", "-".repeat(56));
+
+    let mut cells: Vec<_> = results.by_n_kron.keys().copied().collect();
+    cells.sort_unstable();
+
+    let mut total_chi2 = 0.0;
+    let mut cells_used = 0u64;
+    let mut cells_skipped = 0u64;
+
+    for (n, kron) in cells.drain(..) {
+        let (with_factor, with_growth) = results.by_n_kron[&(n, kron)];
+        if with_factor < min_sample {
+            cells_skipped += 1;
+            continue;
+        }
+
+        let sample = with_factor as f64;
+        let observed_rate = with_growth as f64 / sample;
+        let p = clamp_probability(expected_growth_probability(ell, n, jump));
+        let expected = sample * p;
+        let chi2 = (with_growth as f64 - expected).powi(2) / (expected * (1.0 - p));
+
+        println!(
+            "{:>4} {:>6} {:>10} {:>10.4} {:>10.4} {:>10.4}",
+            n, kron, with_factor, observed_rate, p, chi2
+        );
+
+        total_chi2 += chi2;
+        cells_used += 1;
+    }
+
+    println!("{}", "-".repeat(56));
+    println!(
+        "Aggregate χ² = {:.4} over {} cell(s) ({} skipped, n < {})",
+        total_chi2, cells_used, cells_skipped, min_sample
+    );
+}
+
+/// Write a serialized report to `output` if given, else to stdout
+fn write_report(payload: &str, output: &Option<PathBuf>) -> std::io::Result<()> {
+    match output {
+        Some(path) => std::fs::write(path, payload),
+        None => {
+            print!("{}", payload);
+            Ok(())
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let ells = parse_ells(&args.ell)?;
+    if args.jump == 0 || args.jump > MAX_JUMP {
+        return Err(format!("--jump must be between 1 and {}", MAX_JUMP).into());
+    }
+
+    // Only the "text" format prints the human-readable report to stdout; json/csv
+    // write a single structured payload instead, so progress notes go to stderr.
+    let is_text = args.format != "json" && args.format != "csv";
+    if args.heuristic && !is_text {
+        return Err(
+            "--heuristic is only supported with --format text; the json/csv \
+             export doesn't carry the chi-squared fields yet"
+                .into(),
+        );
+    }
+    macro_rules! status {
+        ($($arg:tt)*) => {
+            if is_text {
+                println!($($arg)*);
+            } else {
+                eprintln!($($arg)*);
+            }
+        };
+    }
+
+    status!("ℓ-adic growth analysis");
+    status!("======================");
+    status!("folder: {:?}", args.folder);
+    status!("ℓ={}", args.ell);
+    status!("D_max={}, files={}", args.d_max, args.files);
+    status!(
+        "Target: all N where ℤ/ℓ^N ℤ → ℤ/ℓ^(N+{})ℤ growth, for ℓ in {:?}",
+        args.jump,
+        ells
+    );
+    status!("Detection mode: {}", args.mode);
+    status!();
+
+    // Aggregate results across all congruence classes, keyed by prime
+    let mut grand_total: ClassResults = HashMap::new();
+    let mut by_class: Vec<((i32, i32), ClassResults)> = Vec::new();
+
+    let opts = GrowthOptions {
+        mode: args.mode.clone(),
+        jump: args.jump,
+        strict_align: args.strict_align,
+        verbose: args.verbose,
+    };
+
+    for (a, m) in CONGRUENCE_CLASSES {
+        let d_total = args.d_max / (args.files * m as i64);
+
+        status!("Processing {} mod {} ...", a, m);
+
+        // Process all file pairs in parallel for this congruence class; each
+        // file pair decodes its fundamental file once and amortizes it across
+        // every requested prime.
+        let results: Vec<_> = (0..args.files)
+            .into_par_iter()
+            .map(|index| {
+                match process_file_pair(&args.folder, a, m, &ells, index, d_total, &opts) {
+                    Ok(r) => r,
+                    Err(e) => {
+                        eprintln!("Error processing file {} for {}mod{}: {}", index, a, m, e);
+                        if opts.strict_align {
+                            std::process::exit(1);
+                        }
+                        HashMap::new()
+                    }
+                }
+            })
+            .collect();
+
+        // Merge per-prime results for this congruence class
+        let mut class_results: ClassResults = HashMap::new();
+        for per_prime in results {
+            for (ell, r) in per_prime {
+                class_results.entry(ell).or_default().merge(r);
+            }
+        }
+
+        for (&ell, r) in &class_results {
+            grand_total.entry(ell).or_default().merge(r.clone());
+        }
+        by_class.push(((a, m), class_results));
+    }
+
+    // Output per-class summaries
+    if is_text && args.heuristic {
+        println!();
+        println!("Cohen-Lenstra goodness-of-fit by congruence class");
+        println!("==================================================");
+        for ((a, m), class_results) in &by_class {
+            let mut class_ells: Vec<_> = class_results.keys().copied().collect();
+            class_ells.sort_unstable();
+            for ell in class_ells {
+                print_heuristic_report(
+                    &format!("{} mod {}, ℓ={}", a, m, ell),
+                    &class_results[&ell],
+                    ell,
+                    args.min_sample,
+                    args.jump,
+                );
+            }
+        }
+
+        let mut grand_ells: Vec<_> = grand_total.keys().copied().collect();
+        grand_ells.sort_unstable();
+        for ell in grand_ells {
+            print_heuristic_report(
+                &format!("Grand Total (all congruence classes), ℓ={}", ell),
+                &grand_total[&ell],
+                ell,
+                args.min_sample,
+                args.jump,
+            );
+        }
+    } else if is_text {
+        println!();
+        println!("Results by congruence class");
+        println!("===========================");
+        for ((a, m), class_results) in &by_class {
+            println!();
+            println!("{} mod {}:", a, m);
+
+            let mut class_ells: Vec<_> = class_results.keys().copied().collect();
+            class_ells.sort_unstable();
+            for ell in class_ells {
+                println!("  ℓ={}:", ell);
+                print_class_report(&class_results[&ell], args.jump);
+            }
+        }
+
+        // Output grand totals
+        let mut grand_ells: Vec<_> = grand_total.keys().copied().collect();
+        grand_ells.sort_unstable();
+        for ell in grand_ells {
+            println!();
+            println!("Grand Total (all congruence classes), ℓ={}", ell);
+            println!("====================================");
+            print_grand_total_report(&grand_total[&ell], ell, args.jump);
+        }
+    }
+
+    if !is_text {
+        let payload = match args.format.as_str() {
+            "csv" => build_csv_report(&by_class, &grand_total, &opts, &ells),
+            _ => build_json_report(&by_class, &grand_total, &opts, &ells),
+        };
+        write_report(&payload, &args.output)?;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts(mode: &str, strict_align: bool) -> GrowthOptions {
+        GrowthOptions {
+            mode: mode.to_string(),
+            jump: 1,
+            strict_align,
+            verbose: false,
+        }
+    }
+
+    #[test]
+    fn lenient_mode_skips_a_single_missing_row() {
+        let fund_rows = vec![
+            FundRow {
+                d: 100,
+                invariants: vec![4],
+            },
+            FundRow {
+                d: 108,
+                invariants: vec![4],
+            },
+            FundRow {
+                d: 116,
+                invariants: vec![4],
+            },
+        ];
+        // d=108 is missing from the ell file
+        let ell_rows = vec![
+            EllRow {
+                d: 100,
+                kron: 1,
+                invariants: vec![4],
+            },
+            EllRow {
+                d: 116,
+                kron: 1,
+                invariants: vec![4],
+            },
+        ];
+
+        let results = process_one_prime(&fund_rows, &ell_rows, 2, &opts("strict", false)).unwrap();
+        assert_eq!(results.total, 2);
+        assert_eq!(results.unmatched, 1);
+    }
+
+    #[test]
+    fn strict_align_aborts_on_the_first_missing_row() {
+        let fund_rows = vec![
+            FundRow {
+                d: 100,
+                invariants: vec![4],
+            },
+            FundRow {
+                d: 108,
+                invariants: vec![4],
+            },
+        ];
+        let ell_rows = vec![EllRow {
+            d: 100,
+            kron: 1,
+            invariants: vec![4],
+        }];
+
+        assert!(process_one_prime(&fund_rows, &ell_rows, 2, &opts("strict", true)).is_err());
+    }
+
+    #[test]
+    fn lenient_mode_still_aborts_on_two_consecutive_missing_rows() {
+        let fund_rows = vec![
+            FundRow {
+                d: 100,
+                invariants: vec![4],
+            },
+            FundRow {
+                d: 108,
+                invariants: vec![4],
+            },
+            FundRow {
+                d: 116,
+                invariants: vec![4],
+            },
+            FundRow {
+                d: 124,
+                invariants: vec![4],
+            },
+        ];
+        // d=108 and d=116 are both missing from the ell file back-to-back
+        let ell_rows = vec![
+            EllRow {
+                d: 100,
+                kron: 1,
+                invariants: vec![4],
+            },
+            EllRow {
+                d: 124,
+                kron: 1,
+                invariants: vec![4],
+            },
+        ];
+
+        let err = process_one_prime(&fund_rows, &ell_rows, 2, &opts("strict", false)).unwrap_err();
+        assert!(err.to_string().contains("consecutive"));
+    }
+
+    #[test]
+    fn strict_align_rejects_a_whole_file_row_count_mismatch_up_front() {
+        let fund_rows = vec![
+            FundRow {
+                d: 100,
+                invariants: vec![4],
+            },
+            FundRow {
+                d: 108,
+                invariants: vec![4],
+            },
+        ];
+        // An extra, otherwise well-formed row at the tail: the two files
+        // disagree on total row count even though every row up to here lines
+        // up, which is exactly the kind of drift that can coincide with a
+        // later discriminant and get silently paired with the wrong invariants.
+        let ell_rows = vec![
+            EllRow {
+                d: 100,
+                kron: 1,
+                invariants: vec![4],
+            },
+            EllRow {
+                d: 108,
+                kron: 1,
+                invariants: vec![4],
+            },
+            EllRow {
+                d: 116,
+                kron: 1,
+                invariants: vec![4],
+            },
+        ];
+
+        assert!(process_one_prime(&fund_rows, &ell_rows, 2, &opts("strict", true)).is_err());
+        // Lenient mode can't recover the dropped fundamental row, but it
+        // should still finish and account for the leftover ell row as unmatched.
+        let results = process_one_prime(&fund_rows, &ell_rows, 2, &opts("strict", false)).unwrap();
+        assert_eq!(results.total, 2);
+        assert_eq!(results.unmatched, 1);
+    }
+
+    #[test]
+    fn coincidental_collision_is_still_caught_via_row_count_mismatch() {
+        // A real, regularly-spaced fund sequence (gap of 8 throughout).
+        let fund_rows = vec![
+            FundRow {
+                d: 100,
+                invariants: vec![4],
+            },
+            FundRow {
+                d: 108,
+                invariants: vec![4],
+            },
+            FundRow {
+                d: 116,
+                invariants: vec![4],
+            },
+        ];
+        // An extra ell row inserted after d=100 with the same gap (8) as its
+        // neighbors: its own d (108) exactly collides with fund's real second
+        // row, so the merge-join pairs it as an ordinary match - no skip ever
+        // fires. Every row after the insertion point shifts by the same gap
+        // (108 -> 116, 116 -> 124), so the genuine d=108 row's invariants end
+        // up paired against fund's d=116 instead, again with an exact,
+        // skip-free D match. Only the resulting whole-file row-count mismatch
+        // (3 fund rows vs 4 ell rows) gives any indication that two of these
+        // three "matches" paired the wrong invariants.
+        let ell_rows = vec![
+            EllRow {
+                d: 100,
+                kron: 1,
+                invariants: vec![4],
+            },
+            EllRow {
+                d: 108,
+                kron: 1,
+                invariants: vec![9],
+            }, // bogus inserted row
+            EllRow {
+                d: 116,
+                kron: 1,
+                invariants: vec![4],
+            }, // really the d=108 row, shifted
+            EllRow {
+                d: 124,
+                kron: 1,
+                invariants: vec![4],
+            }, // really the d=116 row, shifted
+        ];
+
+        let results = process_one_prime(&fund_rows, &ell_rows, 2, &opts("strict", false)).unwrap();
+        // Every fund row found an exact D match, so the mispairing itself
+        // never shows up in `total`/`unmatched` (total=3, unmatched=1,
+        // matching the reviewer-reported numbers; the leftover ell row is
+        // counted like any ordinary unmatched row) - mismatched_files is what
+        // flags the file pair as possibly mispaired.
+        assert_eq!(results.total, 3);
+        assert_eq!(results.unmatched, 1);
+        assert_eq!(results.mismatched_files, 1);
+
+        assert!(process_one_prime(&fund_rows, &ell_rows, 2, &opts("strict", true)).is_err());
+    }
+}